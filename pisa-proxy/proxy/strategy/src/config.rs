@@ -48,6 +48,7 @@ pub struct ReadWriteSplittingDynamic {
 #[serde(rename_all = "lowercase", tag = "type")]
 pub enum Discovery {
     Mha(MasterHighAvailability),
+    GroupReplication(GroupReplicationConfig),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
@@ -84,6 +85,39 @@ pub struct MasterHighAvailability {
     pub read_only_failure_threshold: u64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct GroupReplicationConfig {
+    pub user: String,
+    pub password: String,
+    #[serde(default = "default_group_name")]
+    pub group_name: String,
+    #[serde(default = "default_member_probe_period")]
+    pub member_probe_period: u64,
+    #[serde(default = "default_member_probe_timeout")]
+    pub member_probe_timeout: u64,
+    #[serde(default = "default_member_probe_failure_threshold")]
+    pub member_probe_failure_threshold: u64,
+    #[serde(default)]
+    pub quorum_loss_behavior: QuorumLossBehavior,
+}
+
+/// What to do with the read-write endpoint when the group can no longer
+/// form a quorum (e.g. a majority of members are unreachable).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuorumLossBehavior {
+    /// Keep routing to the last known primary and hope it recovers.
+    KeepLastKnown,
+    /// Reject writes until quorum is re-established.
+    RejectWrites,
+}
+
+impl Default for QuorumLossBehavior {
+    fn default() -> Self {
+        Self::RejectWrites
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RegexRule {
     pub name: String,
@@ -170,3 +204,19 @@ fn default_read_only_timeout() -> u64 {
 fn default_read_only_failure_threshold() -> u64 {
     1
 }
+
+fn default_group_name() -> String {
+    String::new()
+}
+
+fn default_member_probe_period() -> u64 {
+    1000
+}
+
+fn default_member_probe_timeout() -> u64 {
+    6000
+}
+
+fn default_member_probe_failure_threshold() -> u64 {
+    1
+}
@@ -0,0 +1,183 @@
+// Copyright 2022 SphereEx Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structured audit trail for topology changes made by
+//! [`super::monitor_reconcile::MonitorReconcile`]. Every master promotion
+//! or read-node removal is emitted as both a `tracing` event and a
+//! queryable in-memory ring, so "why did the master change at 03:14?" can
+//! be answered after the fact instead of only at the moment `error!` fired.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use tracing::{info, warn};
+
+/// Which monitor observed the change that triggered the audit event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggeringMonitor {
+    Connect,
+    Ping,
+    ReplicationLag,
+    ReadOnly,
+    GroupReplication,
+}
+
+/// The kind of topology transition being recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventKind {
+    MasterPromoted,
+    ReadNodeDropped,
+}
+
+/// A single topology transition, as recorded by the audit subsystem.
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub timestamp_ms: u128,
+    pub kind: AuditEventKind,
+    pub addr: String,
+    pub old_role: Option<String>,
+    pub new_role: Option<String>,
+    pub monitor: TriggeringMonitor,
+    pub metric: Option<u64>,
+}
+
+impl AuditEvent {
+    fn now(
+        kind: AuditEventKind,
+        addr: impl Into<String>,
+        old_role: Option<String>,
+        new_role: Option<String>,
+        monitor: TriggeringMonitor,
+        metric: Option<u64>,
+    ) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        AuditEvent {
+            timestamp_ms,
+            kind,
+            addr: addr.into(),
+            old_role,
+            new_role,
+            monitor,
+            metric,
+        }
+    }
+}
+
+/// Number of past transitions kept queryable in [`AuditRing`].
+const AUDIT_RING_CAPACITY: usize = 256;
+/// Depth of the non-blocking channel feeding the ring. Sized generously so
+/// a burst of transitions in one reconcile cycle never blocks `report`.
+const AUDIT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Write side of the audit subsystem, held by `MonitorReconcile`.
+#[derive(Clone)]
+pub struct AuditLog {
+    sender: Sender<AuditEvent>,
+}
+
+impl AuditLog {
+    /// Creates the audit channel plus the in-memory ring that drains it,
+    /// and spawns the background task that keeps the ring up to date.
+    pub fn new() -> (Self, AuditRing) {
+        let (sender, receiver) = bounded(AUDIT_CHANNEL_CAPACITY);
+        let ring = AuditRing {
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(AUDIT_RING_CAPACITY))),
+        };
+
+        let ring_writer = ring.clone();
+        tokio::task::spawn_blocking(move || {
+            ring_writer.drain_into_ring(receiver);
+        });
+
+        (AuditLog { sender }, ring)
+    }
+
+    /// Records a master promotion. Never blocks `report`: the event is
+    /// emitted as a `tracing` event immediately, and handed off to the
+    /// ring via a non-blocking send that drops the event rather than
+    /// stalling the reconcile loop if the ring's background task is behind.
+    pub fn master_promoted(
+        &self,
+        addr: impl Into<String>,
+        old_role: Option<String>,
+        monitor: TriggeringMonitor,
+    ) {
+        let addr = addr.into();
+        info!(addr = %addr, ?monitor, "master promoted");
+        self.send(AuditEvent::now(
+            AuditEventKind::MasterPromoted,
+            addr,
+            old_role,
+            Some("master".to_string()),
+            monitor,
+            None,
+        ));
+    }
+
+    /// Records a read node being dropped from the read pool.
+    pub fn read_node_dropped(
+        &self,
+        addr: impl Into<String>,
+        monitor: TriggeringMonitor,
+        metric: Option<u64>,
+    ) {
+        let addr = addr.into();
+        info!(addr = %addr, ?monitor, ?metric, "read node dropped");
+        self.send(AuditEvent::now(
+            AuditEventKind::ReadNodeDropped,
+            addr,
+            Some("read".to_string()),
+            None,
+            monitor,
+            metric,
+        ));
+    }
+
+    fn send(&self, event: AuditEvent) {
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(event) {
+            warn!("audit channel full, dropping topology change event");
+        }
+    }
+}
+
+/// Read side: a bounded, queryable history of the last
+/// [`AUDIT_RING_CAPACITY`] topology transitions.
+#[derive(Clone)]
+pub struct AuditRing {
+    events: Arc<Mutex<VecDeque<AuditEvent>>>,
+}
+
+impl AuditRing {
+    fn drain_into_ring(&self, receiver: Receiver<AuditEvent>) {
+        while let Ok(event) = receiver.recv() {
+            let mut events = self.events.lock().unwrap();
+            if events.len() == AUDIT_RING_CAPACITY {
+                events.pop_front();
+            }
+            events.push_back(event);
+        }
+    }
+
+    /// Returns the last transitions recorded, oldest first.
+    pub fn snapshot(&self) -> Vec<AuditEvent> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+}
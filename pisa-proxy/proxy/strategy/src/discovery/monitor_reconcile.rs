@@ -12,49 +12,88 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
 use crossbeam_channel::unbounded;
 use tracing::error;
 
-use crate::{
-    config::ReadWriteSplittingDynamic,
-    readwritesplitting::{dynamic_rw::MonitorResponse, ReadWriteEndpoint},
+use super::{
+    audit::{AuditLog, AuditRing, TriggeringMonitor},
+    monitor_response_ring::{drain_latest, MonitorResponseConsumers},
 };
+use crate::{config::ReadWriteSplittingDynamic, readwritesplitting::ReadWriteEndpoint};
 
 pub struct MonitorReconcile {
-    config: crate::config::Discovery,
+    config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
     rw_endpoint: ReadWriteEndpoint,
+    audit: AuditLog,
 }
 
 use crate::monitors::{
-    connect_monitor::ConnectMonitorResponse, ping_monitor::PingMonitorResponse,
+    connect_monitor::ConnectMonitorResponse,
+    group_replication_monitor::GroupReplicationMonitorResponse, ping_monitor::PingMonitorResponse,
     read_only_monitor::ReadOnlyMonitorResponse,
     replication_lag_monitor::ReplicationLagMonitorResponse,
 };
 
+/// Weight floor for a read node that's at (or past) `max_replication_lag`.
+const MIN_READ_WEIGHT: u64 = 1;
+/// Weight ceiling for a read node with no measured lag.
+const MAX_READ_WEIGHT: u64 = 100;
+
 impl MonitorReconcile {
-    pub fn new(config: ReadWriteSplittingDynamic, rw_endpoint: ReadWriteEndpoint) -> Self {
-        MonitorReconcile { config: config.discovery, rw_endpoint }
+    /// Builds the reconciler and its audit subsystem, returning the ring
+    /// operators can query to answer "why did the master change?".
+    pub fn new(
+        config: ReadWriteSplittingDynamic,
+        rw_endpoint: ReadWriteEndpoint,
+    ) -> (Self, AuditRing) {
+        let (audit, audit_ring) = AuditLog::new();
+        (
+            MonitorReconcile {
+                config: Arc::new(ArcSwap::from_pointee(config)),
+                rw_endpoint,
+                audit,
+            },
+            audit_ring,
+        )
+    }
+
+    /// Returns a handle to the live config so it can be shared with the
+    /// connect/ping/replication-lag/read-only monitor tasks.
+    pub fn config_handle(&self) -> Arc<ArcSwap<ReadWriteSplittingDynamic>> {
+        self.config.clone()
+    }
+
+    /// Atomically replaces the running `ReadWriteSplittingDynamic` config.
+    /// The reconcile loop and any monitor holding this handle pick up the
+    /// new discovery rules, periods, and timeouts on their next iteration.
+    pub fn swap_config(&self, config: ReadWriteSplittingDynamic) {
+        self.config.store(Arc::new(config));
     }
 
     pub fn start_monitor_reconcile(
         &mut self,
         monitor_interval: u64,
-        monitor_response_channel: crate::readwritesplitting::MonitorResponseChannel,
-        monitors_len: usize,
+        monitor_response_consumers: MonitorResponseConsumers,
     ) -> crossbeam_channel::Receiver<ReadWriteEndpoint> {
         let (send, recv) = unbounded();
         let tx = send.clone();
         let rx = recv.clone();
 
         let rw_endpoint = self.rw_endpoint.clone();
+        let config = self.config.clone();
+        let audit = self.audit.clone();
 
         tokio::spawn(async move {
             MonitorReconcile::report(
                 tx,
+                config,
                 monitor_interval,
                 rw_endpoint,
-                monitor_response_channel,
-                monitors_len,
+                monitor_response_consumers,
+                audit,
             )
             .await;
         });
@@ -64,38 +103,113 @@ impl MonitorReconcile {
 
     async fn report(
         s: crossbeam_channel::Sender<ReadWriteEndpoint>,
+        config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
         monitor_interval: u64,
         rw_endpoint: ReadWriteEndpoint,
-        monitor_response_channel: crate::readwritesplitting::MonitorResponseChannel,
-        monitors_len: usize,
+        mut monitor_response_consumers: MonitorResponseConsumers,
+        audit: AuditLog,
     ) {
         let mut connect_monitor_response: Option<ConnectMonitorResponse> = None;
         let mut ping_monitor_response: Option<PingMonitorResponse> = None;
         let mut replication_lag_monitor_response: Option<ReplicationLagMonitorResponse> = None;
         let mut read_only_monitor_response: Option<ReadOnlyMonitorResponse> = None;
+        let mut group_replication_monitor_response: Option<GroupReplicationMonitorResponse> = None;
 
         tokio::task::spawn_blocking(move || {
             let mut pre_rw_endpoint = rw_endpoint.clone();
             loop {
-                let monitor_response_channel = monitor_response_channel.clone();
-                let mut curr_rw_endpoint = rw_endpoint.clone();
-                for _ in 0..monitors_len {
-                    match monitor_response_channel.monitor_response_rx.recv().unwrap() {
-                        MonitorResponse::ConnectMonitorResponse(connect_response) => {
-                            connect_monitor_response = Some(connect_response);
-                        }
-                        MonitorResponse::PingMonitorResponse(ping_response) => {
-                            ping_monitor_response = Some(ping_response);
-                        }
-                        MonitorResponse::ReadOnlyMonitorResponse(read_only_response) => {
-                            read_only_monitor_response = Some(read_only_response);
-                        }
-                        MonitorResponse::ReplicationLagResponse(replication_lag_response) => {
-                            replication_lag_monitor_response = Some(replication_lag_response);
+                // Re-read the live config on every iteration so an operator
+                // pushing a new `ReadWriteSplitting` takes effect immediately,
+                // without restarting the already-running monitor tasks.
+                let discovery = config.load_full().discovery.clone();
+
+                // Drain each ring down to its newest snapshot rather than
+                // blocking on a channel recv; a monitor that hasn't
+                // published since the last cycle just leaves the previous
+                // response in place.
+                if let Some(connect_response) =
+                    drain_latest(&mut monitor_response_consumers.connect)
+                {
+                    connect_monitor_response = Some(connect_response);
+                }
+                if let Some(ping_response) = drain_latest(&mut monitor_response_consumers.ping) {
+                    ping_monitor_response = Some(ping_response);
+                }
+                if let Some(read_only_response) =
+                    drain_latest(&mut monitor_response_consumers.read_only)
+                {
+                    read_only_monitor_response = Some(read_only_response);
+                }
+                if let Some(replication_lag_response) =
+                    drain_latest(&mut monitor_response_consumers.replication_lag)
+                {
+                    replication_lag_monitor_response = Some(replication_lag_response);
+                }
+                if let Some(group_replication_response) =
+                    drain_latest(&mut monitor_response_consumers.group_replication)
+                {
+                    group_replication_monitor_response = Some(group_replication_response);
+                }
+
+                // A Group Replication / InnoDB Cluster group reports its own
+                // authoritative member roles, so rebuild the endpoint
+                // deterministically from those instead of inferring the
+                // master from the ping/read-only heuristics below.
+                if let crate::config::Discovery::GroupReplication(group_config) = &discovery {
+                    if let Some(group_response) = group_replication_monitor_response.as_ref() {
+                        // Candidates come from the static `rw_endpoint`
+                        // baseline (like the MHA branch below), not
+                        // `pre_rw_endpoint`: a member that drops out of both
+                        // pools for one cycle must still be a candidate when
+                        // it reports back online, or it can never rejoin.
+                        // `curr_rw_endpoint` itself still starts as a clone
+                        // of `pre_rw_endpoint` so audit emission, which
+                        // diffs against it, stays gated on the real prior
+                        // state instead of re-firing every cycle.
+                        let mut curr_rw_endpoint = pre_rw_endpoint.clone();
+                        reconcile_from_group_replication(
+                            &mut curr_rw_endpoint,
+                            &rw_endpoint,
+                            group_response,
+                            group_config,
+                            &audit,
+                        );
+
+                        if pre_rw_endpoint != curr_rw_endpoint {
+                            if let Err(err) = s.send(curr_rw_endpoint.clone()) {
+                                error!("send read write endpoint err: {:#?}", err);
+                            }
                         }
+                        pre_rw_endpoint = curr_rw_endpoint;
                     }
+
+                    std::thread::sleep(std::time::Duration::from_millis(monitor_interval));
+                    continue;
                 }
 
+                // The connect monitor hasn't published yet, nothing to
+                // reconcile this cycle.
+                if connect_monitor_response.is_none() {
+                    std::thread::sleep(std::time::Duration::from_millis(monitor_interval));
+                    continue;
+                }
+
+                // GR is handled (and `continue`s) above, so the discovery
+                // config here is always Mha.
+                let mha_config = match &discovery {
+                    crate::config::Discovery::Mha(mha) => mha.clone(),
+                    crate::config::Discovery::GroupReplication(_) => {
+                        unreachable!("Discovery::GroupReplication is handled earlier in this loop")
+                    }
+                };
+
+                // Rebuilt from the static baseline every cycle (not
+                // `pre_rw_endpoint`) so a read node that recovers can
+                // rejoin the pool; audit emission below is gated against
+                // `pre_rw_endpoint` instead, so a steady-state topology
+                // doesn't re-emit the same transition every cycle.
+                let mut curr_rw_endpoint = rw_endpoint.clone();
+
                 for (_read_write_connect_addr, read_write_connect_status) in
                     &connect_monitor_response.as_ref().unwrap().readwrite
                 {
@@ -115,6 +229,17 @@ impl MonitorReconcile {
                                                 match read_only_monitor_response.as_ref().unwrap().roles.get(&read_endpoint.addr).unwrap() {
                                                     // slave change to master
                                                     crate::monitors::read_only_monitor::NodeRole::Master => {
+                                                        if !pre_rw_endpoint
+                                                            .readwrite
+                                                            .iter()
+                                                            .any(|e| e.addr == read_endpoint.addr)
+                                                        {
+                                                            audit.master_promoted(
+                                                                read_endpoint.addr.clone(),
+                                                                Some("slave".to_string()),
+                                                                TriggeringMonitor::Ping,
+                                                            );
+                                                        }
                                                         // clean readwrite list
                                                         curr_rw_endpoint.readwrite = vec![];
                                                         // add new read write into master list
@@ -141,6 +266,17 @@ impl MonitorReconcile {
                                         match read_only_response.roles.get(&read_endpoint.addr).unwrap() {
                                             //slave change to master
                                             crate::monitors::read_only_monitor::NodeRole::Master => {
+                                                if !pre_rw_endpoint
+                                                    .readwrite
+                                                    .iter()
+                                                    .any(|e| e.addr == read_endpoint.addr)
+                                                {
+                                                    audit.master_promoted(
+                                                        read_endpoint.addr.clone(),
+                                                        Some("slave".to_string()),
+                                                        TriggeringMonitor::Connect,
+                                                    );
+                                                }
                                                 curr_rw_endpoint.readwrite = vec![];
                                                 // add new read write into master list
                                                 curr_rw_endpoint.readwrite.push(read_endpoint);
@@ -168,50 +304,73 @@ impl MonitorReconcile {
                                     {
                                         match read_ping_status {
                                             crate::monitors::ping_monitor::PingStatus::PingOk => {
-                                                match replication_lag_monitor_response.clone() {
-                                                    Some(replication_lag_response) => {
-                                                        for (replication_lag_addr, lag_status) in
-                                                            &replication_lag_response.latency
-                                                        {
-                                                            if !lag_status.is_latency {
-                                                                match curr_rw_endpoint.read.iter().find(
-                                                                    |r| r.addr.eq(replication_lag_addr),
-                                                                ) {
-                                                                    Some(_) => {}
-                                                                    None => {
-                                                                        curr_rw_endpoint.read.append(
-                                                                            &mut curr_rw_endpoint
-                                                                                .read
-                                                                                .clone(),
-                                                                        );
-                                                                    }
-                                                                }
-                                                                continue;
-                                                            } else {
-                                                                // add replication_lag_addr to read_write list
-                                                                curr_rw_endpoint.read.remove(
-                                                                    rw_endpoint
-                                                                        .read
-                                                                        .iter()
-                                                                        .position(|r| {
-                                                                            r.addr.eq(replication_lag_addr)
-                                                                        })
-                                                                        .unwrap(),
+                                                if let Some(replication_lag_response) =
+                                                    replication_lag_monitor_response.clone()
+                                                {
+                                                    for (replication_lag_addr, lag_status) in
+                                                        &replication_lag_response.latency
+                                                    {
+                                                        if lag_status.is_latency {
+                                                            // past max_replication_lag: drop the node
+                                                            // entirely instead of giving it any weight
+                                                            if pre_rw_endpoint
+                                                                .read
+                                                                .iter()
+                                                                .any(|r| r.addr.eq(replication_lag_addr))
+                                                            {
+                                                                audit.read_node_dropped(
+                                                                    replication_lag_addr.clone(),
+                                                                    TriggeringMonitor::ReplicationLag,
+                                                                    lag_status.seconds_behind_master,
                                                                 );
                                                             }
+                                                            curr_rw_endpoint
+                                                                .read
+                                                                .retain(|r| !r.addr.eq(replication_lag_addr));
+                                                        } else {
+                                                            // healthy: weight inversely proportional to
+                                                            // the measured lag, so a replica at 0.5s and
+                                                            // one at 4s (both under threshold) don't
+                                                            // converge to the same weight.
+                                                            let lag_secs = lag_status
+                                                                .seconds_behind_master
+                                                                .unwrap_or(0);
+                                                            let max_lag_secs =
+                                                                (mha_config.max_replication_lag / 1000)
+                                                                    .max(1);
+                                                            let headroom =
+                                                                max_lag_secs.saturating_sub(lag_secs);
+                                                            let weight = (MIN_READ_WEIGHT
+                                                                + headroom
+                                                                    * (MAX_READ_WEIGHT - MIN_READ_WEIGHT)
+                                                                    / max_lag_secs)
+                                                                .clamp(MIN_READ_WEIGHT, MAX_READ_WEIGHT);
+                                                            if let Some(endpoint) = curr_rw_endpoint
+                                                                .read
+                                                                .iter_mut()
+                                                                .find(|r| r.addr.eq(replication_lag_addr))
+                                                            {
+                                                                endpoint.weight = weight;
+                                                            }
                                                         }
                                                     }
-                                                    None => {}
                                                 }
                                             }
                                             crate::monitors::ping_monitor::PingStatus::PingNotOk => {
-                                                curr_rw_endpoint.read.remove(
-                                                    rw_endpoint
-                                                        .read
-                                                        .iter()
-                                                        .position(|r| r.addr.eq(&read_ping_addr))
-                                                        .unwrap(),
-                                                );
+                                                if pre_rw_endpoint
+                                                    .read
+                                                    .iter()
+                                                    .any(|r| r.addr.eq(&read_ping_addr))
+                                                {
+                                                    audit.read_node_dropped(
+                                                        read_ping_addr.clone(),
+                                                        TriggeringMonitor::Ping,
+                                                        None,
+                                                    );
+                                                }
+                                                curr_rw_endpoint
+                                                    .read
+                                                    .retain(|r| !r.addr.eq(&read_ping_addr));
                                             }
                                         }
                                     }
@@ -220,9 +379,14 @@ impl MonitorReconcile {
                             }
                         }
                         crate::monitors::connect_monitor::ConnectStatus::Disconnected => {
-                            curr_rw_endpoint.read.remove(
-                                rw_endpoint.read.iter().position(|r| r.addr.eq(read_addr)).unwrap(),
-                            );
+                            if pre_rw_endpoint.read.iter().any(|r| r.addr.eq(read_addr)) {
+                                audit.read_node_dropped(
+                                    read_addr.clone(),
+                                    TriggeringMonitor::Connect,
+                                    None,
+                                );
+                            }
+                            curr_rw_endpoint.read.retain(|r| !r.addr.eq(read_addr));
                         }
                     }
                 }
@@ -240,3 +404,125 @@ impl MonitorReconcile {
         });
     }
 }
+
+/// Rebuilds `curr_rw_endpoint` from the group's authoritative member roles
+/// instead of the connect/ping/read-only promotion guesswork used by MHA.
+/// Endpoints keep their configured connection details; only which pool
+/// (`readwrite` vs `read`) they belong to, and a read endpoint's weight,
+/// change.
+///
+/// Candidates are drawn from `baseline` (the statically configured
+/// `rw_endpoint`), not from `curr_rw_endpoint` itself: a member that
+/// reports OFFLINE for one cycle drops out of `curr_rw_endpoint`'s
+/// readwrite/read pools, and filtering candidates from there instead of
+/// `baseline` would mean it can never be placed back in either pool once
+/// it's online again. `curr_rw_endpoint`'s incoming state is still used
+/// to compute `was_readwrite`, so audit emission is gated against the
+/// real prior topology instead of re-firing every cycle.
+fn reconcile_from_group_replication(
+    curr_rw_endpoint: &mut ReadWriteEndpoint,
+    baseline: &ReadWriteEndpoint,
+    group_response: &GroupReplicationMonitorResponse,
+    group_config: &crate::config::GroupReplicationConfig,
+    audit: &AuditLog,
+) {
+    let known_endpoints: Vec<_> = baseline
+        .readwrite
+        .iter()
+        .chain(baseline.read.iter())
+        .cloned()
+        .collect();
+    let was_readwrite: std::collections::HashSet<_> = curr_rw_endpoint
+        .readwrite
+        .iter()
+        .map(|e| e.addr.to_lowercase())
+        .collect();
+
+    // `MEMBER_HOST`/`MEMBER_PORT` as reported by
+    // `replication_group_members` are matched against the configured
+    // `Endpoint::addr` case-insensitively (DNS names aren't
+    // case-sensitive), but otherwise must match exactly: an IP in config
+    // against a hostname reported by MySQL (or vice versa) won't match
+    // and that endpoint is silently excluded from every pool.
+    let primaries_raw = group_response.primaries();
+    let primaries: std::collections::HashSet<_> = primaries_raw
+        .iter()
+        .map(|addr| addr.to_lowercase())
+        .collect();
+    let secondaries: std::collections::HashSet<_> = group_response
+        .secondaries()
+        .into_iter()
+        .map(|addr| addr.to_lowercase())
+        .collect();
+
+    if primaries.is_empty() {
+        // Quorum lost: the group can't agree on a primary.
+        match group_config.quorum_loss_behavior {
+            crate::config::QuorumLossBehavior::RejectWrites => {
+                curr_rw_endpoint.readwrite = vec![];
+            }
+            crate::config::QuorumLossBehavior::KeepLastKnown => {
+                // Leave `readwrite` as it was and hope the last known
+                // primary recovers; don't clear it out from under in-flight
+                // writes on a transient membership blip.
+            }
+        }
+    } else {
+        curr_rw_endpoint.readwrite = known_endpoints
+            .iter()
+            .filter(|e| primaries.contains(&e.addr.to_lowercase()))
+            .cloned()
+            .collect();
+    }
+
+    // In multi-primary mode every primary can also serve reads, so weight
+    // them alongside the secondaries instead of leaving them out of `read`
+    // entirely.
+    let read_candidates: Vec<_> = if group_response.members.values().any(|status| {
+        status.mode == crate::monitors::group_replication_monitor::ReplicationMode::MultiPrimary
+    }) {
+        known_endpoints
+            .iter()
+            .filter(|e| {
+                let addr = e.addr.to_lowercase();
+                secondaries.contains(&addr) || primaries.contains(&addr)
+            })
+            .cloned()
+            .collect()
+    } else {
+        known_endpoints
+            .iter()
+            .filter(|e| secondaries.contains(&e.addr.to_lowercase()))
+            .cloned()
+            .collect()
+    };
+
+    let was_read: Vec<String> = curr_rw_endpoint.read.iter().map(|e| e.addr.clone()).collect();
+    curr_rw_endpoint.read = read_candidates
+        .into_iter()
+        .map(|mut endpoint| {
+            let lag = group_response
+                .members
+                .get(&endpoint.addr.to_lowercase())
+                .map(|status| status.applier_queue_lag)
+                .unwrap_or(0);
+            // Fewer queued transactions behind the group means more weight;
+            // MAX_READ_WEIGHT caps it the same way the MHA ramp-up does.
+            endpoint.weight = MAX_READ_WEIGHT
+                .saturating_sub(lag.min(MAX_READ_WEIGHT))
+                .max(MIN_READ_WEIGHT);
+            endpoint
+        })
+        .collect();
+
+    for addr in &primaries_raw {
+        if !was_readwrite.contains(&addr.to_lowercase()) {
+            audit.master_promoted(addr.clone(), None, TriggeringMonitor::GroupReplication);
+        }
+    }
+    for addr in &was_read {
+        if !curr_rw_endpoint.read.iter().any(|e| e.addr.eq_ignore_ascii_case(addr)) {
+            audit.read_node_dropped(addr.clone(), TriggeringMonitor::GroupReplication, None);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2022 SphereEx Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lock-free "latest value" mailbox that carries monitor responses into
+//! [`super::monitor_reconcile::MonitorReconcile`]. Each monitor kind gets
+//! its own single-slot `ArcSwapOption`, so a slow monitor simply has its
+//! stale snapshot overwritten and a publish never has to be dropped: a
+//! bounded ring (the original design here) made a full buffer reject the
+//! *newest* publish while the reconciler kept draining the *stale* queued
+//! ones, the opposite of what "let a slow monitor overwrite stale data"
+//! needs.
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+use crate::monitors::{
+    connect_monitor::ConnectMonitorResponse,
+    group_replication_monitor::GroupReplicationMonitorResponse, ping_monitor::PingMonitorResponse,
+    read_only_monitor::ReadOnlyMonitorResponse,
+    replication_lag_monitor::ReplicationLagMonitorResponse,
+};
+
+/// Producer half, one per monitor task. `publish_latest` always succeeds,
+/// overwriting whatever was previously in the slot.
+pub struct MonitorResponseProducer<T> {
+    slot: Arc<ArcSwapOption<T>>,
+}
+
+/// Consumer half, owned by `MonitorReconcile::report`. `drain_latest` takes
+/// the slot's current value, if any, leaving it empty until the next publish.
+pub struct MonitorResponseConsumer<T> {
+    slot: Arc<ArcSwapOption<T>>,
+}
+
+pub struct MonitorResponseProducers {
+    pub connect: MonitorResponseProducer<ConnectMonitorResponse>,
+    pub ping: MonitorResponseProducer<PingMonitorResponse>,
+    pub read_only: MonitorResponseProducer<ReadOnlyMonitorResponse>,
+    pub replication_lag: MonitorResponseProducer<ReplicationLagMonitorResponse>,
+    /// Only fed when `discovery` is `Discovery::GroupReplication`.
+    pub group_replication: MonitorResponseProducer<GroupReplicationMonitorResponse>,
+}
+
+pub struct MonitorResponseConsumers {
+    pub connect: MonitorResponseConsumer<ConnectMonitorResponse>,
+    pub ping: MonitorResponseConsumer<PingMonitorResponse>,
+    pub read_only: MonitorResponseConsumer<ReadOnlyMonitorResponse>,
+    pub replication_lag: MonitorResponseConsumer<ReplicationLagMonitorResponse>,
+    pub group_replication: MonitorResponseConsumer<GroupReplicationMonitorResponse>,
+}
+
+fn mailbox<T>() -> (MonitorResponseProducer<T>, MonitorResponseConsumer<T>) {
+    let slot = Arc::new(ArcSwapOption::empty());
+    (
+        MonitorResponseProducer { slot: slot.clone() },
+        MonitorResponseConsumer { slot },
+    )
+}
+
+/// Builds the five mailboxes that make up the monitor response pipeline.
+pub fn monitor_response_ring() -> (MonitorResponseProducers, MonitorResponseConsumers) {
+    let (connect_p, connect_c) = mailbox();
+    let (ping_p, ping_c) = mailbox();
+    let (read_only_p, read_only_c) = mailbox();
+    let (replication_lag_p, replication_lag_c) = mailbox();
+    let (group_replication_p, group_replication_c) = mailbox();
+
+    (
+        MonitorResponseProducers {
+            connect: connect_p,
+            ping: ping_p,
+            read_only: read_only_p,
+            replication_lag: replication_lag_p,
+            group_replication: group_replication_p,
+        },
+        MonitorResponseConsumers {
+            connect: connect_c,
+            ping: ping_c,
+            read_only: read_only_c,
+            replication_lag: replication_lag_c,
+            group_replication: group_replication_c,
+        },
+    )
+}
+
+/// Publishes `value` as the monitor's latest response, overwriting
+/// whatever was there before. Unlike a bounded queue this never fails and
+/// never drops the newest publish in favor of a stale one.
+pub fn publish_latest<T>(producer: &mut MonitorResponseProducer<T>, value: T) {
+    producer.slot.store(Some(Arc::new(value)));
+}
+
+/// Takes the slot's current value, if any, and empties it. Returns `None`
+/// if the monitor hasn't published since the last drain.
+pub fn drain_latest<T: Clone>(consumer: &mut MonitorResponseConsumer<T>) -> Option<T> {
+    consumer.slot.swap(None).map(|value| {
+        // publish_latest never keeps its own clone around, so this Arc is
+        // almost always uniquely held; only fall back to cloning out of it
+        // if something else (e.g. a concurrent swap) still holds a ref.
+        Arc::try_unwrap(value).unwrap_or_else(|shared| (*shared).clone())
+    })
+}
@@ -0,0 +1,102 @@
+// Copyright 2022 SphereEx Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Periodically checks whether each configured endpoint accepts a TCP
+//! connection, and publishes the result into the monitor response ring
+//! that [`crate::discovery::monitor_reconcile::MonitorReconcile`] drains.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use tokio::net::TcpStream;
+
+use crate::{
+    config::{Discovery, ReadWriteSplittingDynamic},
+    discovery::monitor_response_ring::{publish_latest, MonitorResponseProducer},
+    readwritesplitting::ReadWriteEndpoint,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectStatus {
+    Connected,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectMonitorResponse {
+    pub readwrite: HashMap<String, ConnectStatus>,
+    pub read: HashMap<String, ConnectStatus>,
+}
+
+pub struct ConnectMonitor {
+    config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+    rw_endpoint: ReadWriteEndpoint,
+    producer: MonitorResponseProducer<ConnectMonitorResponse>,
+}
+
+impl ConnectMonitor {
+    pub fn new(
+        config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+        rw_endpoint: ReadWriteEndpoint,
+        producer: MonitorResponseProducer<ConnectMonitorResponse>,
+    ) -> Self {
+        ConnectMonitor {
+            config,
+            rw_endpoint,
+            producer,
+        }
+    }
+
+    /// Spawns the monitor loop. Reloads `connect_period`/`connect_timeout`
+    /// from the live config every iteration, so a config swap takes effect
+    /// without restarting this task.
+    pub fn spawn(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let mha = match &self.config.load_full().discovery {
+                    Discovery::Mha(mha) => mha.clone(),
+                    // Connect checks are an MHA concept; Group Replication
+                    // discovers membership through replication_group_members.
+                    Discovery::GroupReplication(_) => {
+                        tokio::time::sleep(Duration::from_millis(1000)).await;
+                        continue;
+                    }
+                };
+
+                let timeout = Duration::from_millis(mha.connect_timeout);
+                let mut readwrite = HashMap::new();
+                for endpoint in &self.rw_endpoint.readwrite {
+                    readwrite.insert(endpoint.addr.clone(), probe(&endpoint.addr, timeout).await);
+                }
+                let mut read = HashMap::new();
+                for endpoint in &self.rw_endpoint.read {
+                    read.insert(endpoint.addr.clone(), probe(&endpoint.addr, timeout).await);
+                }
+
+                publish_latest(
+                    &mut self.producer,
+                    ConnectMonitorResponse { readwrite, read },
+                );
+                tokio::time::sleep(Duration::from_millis(mha.connect_period)).await;
+            }
+        })
+    }
+}
+
+async fn probe(addr: &str, timeout: Duration) -> ConnectStatus {
+    match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => ConnectStatus::Connected,
+        _ => ConnectStatus::Disconnected,
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2022 SphereEx Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Discovers the primary and secondaries of a MySQL Group Replication /
+//! InnoDB Cluster by querying `performance_schema.replication_group_members`
+//! instead of inferring the master from `read_only`/ping heuristics.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use mysql_async::prelude::Queryable;
+use tracing::warn;
+
+use crate::{
+    config::{Discovery, GroupReplicationConfig, ReadWriteSplittingDynamic},
+    discovery::monitor_response_ring::{publish_latest, MonitorResponseProducer},
+    readwritesplitting::ReadWriteEndpoint,
+};
+
+/// A member's role as reported by `replication_group_members`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberRole {
+    Primary,
+    Secondary,
+}
+
+/// Whether the group is running in single-primary or multi-primary mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicationMode {
+    SinglePrimary,
+    MultiPrimary,
+}
+
+/// Per-member status as observed on the most recent probe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupReplicationMemberStatus {
+    pub role: MemberRole,
+    pub mode: ReplicationMode,
+    /// Whether `MEMBER_STATE` reported the member as `ONLINE`.
+    pub online: bool,
+    /// Applier-queue-based lag, in number of queued transactions.
+    pub applier_queue_lag: u64,
+}
+
+/// Snapshot of the whole group, keyed by member address, for one probe.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupReplicationMonitorResponse {
+    pub members: HashMap<String, GroupReplicationMemberStatus>,
+}
+
+impl GroupReplicationMonitorResponse {
+    /// Addresses reporting `MemberRole::Primary` and `online`. Single-primary
+    /// groups should have exactly one; multi-primary groups may have more.
+    pub fn primaries(&self) -> Vec<String> {
+        self.members
+            .iter()
+            .filter(|(_, status)| status.online && status.role == MemberRole::Primary)
+            .map(|(addr, _)| addr.clone())
+            .collect()
+    }
+
+    /// Addresses reporting `MemberRole::Secondary` and `online`.
+    pub fn secondaries(&self) -> Vec<String> {
+        self.members
+            .iter()
+            .filter(|(_, status)| status.online && status.role == MemberRole::Secondary)
+            .map(|(addr, _)| addr.clone())
+            .collect()
+    }
+}
+
+/// Query issued against any reachable member to discover group membership,
+/// role, and lag. `MEMBER_STATE`/`MEMBER_ROLE` come from
+/// `replication_group_members`; `COUNT_TRANSACTIONS_REMOTE_IN_APPLIER_QUEUE`
+/// comes from `replication_group_member_stats`, joined on `MEMBER_ID`, and
+/// approximates lag for weighted read routing.
+pub const GROUP_REPLICATION_MEMBERS_QUERY: &str = "SELECT \
+    m.MEMBER_HOST, m.MEMBER_PORT, m.MEMBER_STATE, m.MEMBER_ROLE, \
+    s.COUNT_TRANSACTIONS_REMOTE_IN_APPLIER_QUEUE AS applier_queue_lag \
+    FROM performance_schema.replication_group_members m \
+    JOIN performance_schema.replication_group_member_stats s \
+    ON s.MEMBER_ID = m.MEMBER_ID";
+
+pub struct GroupReplicationMonitor {
+    config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+    rw_endpoint: ReadWriteEndpoint,
+    producer: MonitorResponseProducer<GroupReplicationMonitorResponse>,
+}
+
+impl GroupReplicationMonitor {
+    pub fn new(
+        config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+        rw_endpoint: ReadWriteEndpoint,
+        producer: MonitorResponseProducer<GroupReplicationMonitorResponse>,
+    ) -> Self {
+        GroupReplicationMonitor {
+            config,
+            rw_endpoint,
+            producer,
+        }
+    }
+
+    /// Spawns the probe loop. Reloads `member_probe_period`/
+    /// `member_probe_timeout`/`member_probe_failure_threshold` from the live
+    /// config every iteration. Any already-known member can answer the
+    /// membership query, so seed hosts are the `rw_endpoint` this monitor
+    /// was built with. A probe that reaches none of them publishes nothing,
+    /// so the previous snapshot is kept rather than an empty one (which
+    /// would look like quorum loss); after `member_probe_failure_threshold`
+    /// consecutive failures a warning is logged.
+    pub fn spawn(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut consecutive_failures: u64 = 0;
+            loop {
+                let group_config = match &self.config.load_full().discovery {
+                    Discovery::GroupReplication(group_config) => group_config.clone(),
+                    Discovery::Mha(_) => {
+                        tokio::time::sleep(Duration::from_millis(1000)).await;
+                        continue;
+                    }
+                };
+
+                let timeout = Duration::from_millis(group_config.member_probe_timeout);
+                match probe(&self.rw_endpoint, &group_config, timeout).await {
+                    Some(response) => {
+                        consecutive_failures = 0;
+                        publish_latest(&mut self.producer, response);
+                    }
+                    None => {
+                        consecutive_failures += 1;
+                        if consecutive_failures >= group_config.member_probe_failure_threshold {
+                            warn!(
+                                consecutive_failures,
+                                "group replication monitor could not reach any known member"
+                            );
+                        }
+                    }
+                }
+
+                tokio::time::sleep(Duration::from_millis(group_config.member_probe_period)).await;
+            }
+        })
+    }
+}
+
+async fn probe(
+    rw_endpoint: &ReadWriteEndpoint,
+    group_config: &GroupReplicationConfig,
+    timeout: Duration,
+) -> Option<GroupReplicationMonitorResponse> {
+    for endpoint in rw_endpoint.readwrite.iter().chain(rw_endpoint.read.iter()) {
+        let url = format!(
+            "mysql://{}:{}@{}",
+            group_config.user, group_config.password, endpoint.addr
+        );
+        let query = async {
+            let pool = mysql_async::Pool::new(url.as_str());
+            let mut conn = pool.get_conn().await?;
+            let rows: Vec<(String, u16, String, String, u64)> =
+                conn.query(GROUP_REPLICATION_MEMBERS_QUERY).await?;
+            pool.disconnect().await?;
+            Ok::<_, mysql_async::Error>(rows)
+        };
+
+        match tokio::time::timeout(timeout, query).await {
+            Ok(Ok(rows)) => return Some(to_response(rows)),
+            Ok(Err(err)) => {
+                warn!(addr = %endpoint.addr, %err, "group replication member probe failed");
+            }
+            Err(_) => {
+                warn!(addr = %endpoint.addr, "group replication member probe timed out");
+            }
+        }
+    }
+    None
+}
+
+/// `replication_group_members` reports multi-primary mode as one row per
+/// member, each with `MEMBER_ROLE = 'PRIMARY'`; single-primary mode has
+/// exactly one such row. Counting online primaries across the result set
+/// tells the two apart without an extra query.
+///
+/// Member addresses are keyed by `MEMBER_HOST:MEMBER_PORT`, lowercased
+/// since DNS names aren't case-sensitive;
+/// `reconcile_from_group_replication` lowercases the configured
+/// `Endpoint::addr` the same way before comparing. There's no DNS/IP
+/// equivalence check beyond that: a configured IP won't match a hostname
+/// MySQL reports for the same host (or vice versa), so `Endpoint::addr`
+/// must be configured as whatever `MEMBER_HOST` actually reports.
+fn to_response(rows: Vec<(String, u16, String, String, u64)>) -> GroupReplicationMonitorResponse {
+    let online_primaries = rows
+        .iter()
+        .filter(|(_, _, state, role, _)| state == "ONLINE" && role == "PRIMARY")
+        .count();
+    let mode = if online_primaries > 1 {
+        ReplicationMode::MultiPrimary
+    } else {
+        ReplicationMode::SinglePrimary
+    };
+
+    let members = rows
+        .into_iter()
+        .map(|(host, port, state, role, applier_queue_lag)| {
+            let role = if role == "PRIMARY" {
+                MemberRole::Primary
+            } else {
+                MemberRole::Secondary
+            };
+            (
+                format!("{}:{port}", host.to_lowercase()),
+                GroupReplicationMemberStatus {
+                    role,
+                    mode,
+                    online: state == "ONLINE",
+                    applier_queue_lag,
+                },
+            )
+        })
+        .collect();
+
+    GroupReplicationMonitorResponse { members }
+}
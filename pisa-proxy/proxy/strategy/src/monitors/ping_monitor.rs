@@ -0,0 +1,120 @@
+// Copyright 2022 SphereEx Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Periodically issues a lightweight `SELECT 1` against every configured
+//! endpoint to tell a connected-but-wedged MySQL instance apart from one
+//! that's actually serving queries, and publishes the result into the
+//! monitor response ring that `MonitorReconcile::report` drains.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use tracing::warn;
+
+use crate::{
+    config::{Discovery, ReadWriteSplittingDynamic},
+    discovery::monitor_response_ring::{publish_latest, MonitorResponseProducer},
+    readwritesplitting::ReadWriteEndpoint,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingStatus {
+    PingOk,
+    PingNotOk,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PingMonitorResponse {
+    pub readwrite: HashMap<String, PingStatus>,
+    pub read: HashMap<String, PingStatus>,
+}
+
+pub struct PingMonitor {
+    config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+    rw_endpoint: ReadWriteEndpoint,
+    producer: MonitorResponseProducer<PingMonitorResponse>,
+}
+
+impl PingMonitor {
+    pub fn new(
+        config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+        rw_endpoint: ReadWriteEndpoint,
+        producer: MonitorResponseProducer<PingMonitorResponse>,
+    ) -> Self {
+        PingMonitor {
+            config,
+            rw_endpoint,
+            producer,
+        }
+    }
+
+    /// Spawns the monitor loop. Reloads `ping_period`/`ping_timeout` from the
+    /// live config every iteration, so a config swap takes effect without
+    /// restarting this task.
+    pub fn spawn(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let mha = match &self.config.load_full().discovery {
+                    Discovery::Mha(mha) => mha.clone(),
+                    Discovery::GroupReplication(_) => {
+                        tokio::time::sleep(Duration::from_millis(1000)).await;
+                        continue;
+                    }
+                };
+
+                let timeout = Duration::from_millis(mha.ping_timeout);
+                let mut readwrite = HashMap::new();
+                for endpoint in &self.rw_endpoint.readwrite {
+                    readwrite.insert(
+                        endpoint.addr.clone(),
+                        ping(&endpoint.addr, &mha.user, &mha.password, timeout).await,
+                    );
+                }
+                let mut read = HashMap::new();
+                for endpoint in &self.rw_endpoint.read {
+                    read.insert(
+                        endpoint.addr.clone(),
+                        ping(&endpoint.addr, &mha.user, &mha.password, timeout).await,
+                    );
+                }
+
+                publish_latest(&mut self.producer, PingMonitorResponse { readwrite, read });
+                tokio::time::sleep(Duration::from_millis(mha.ping_period)).await;
+            }
+        })
+    }
+}
+
+async fn ping(addr: &str, user: &str, password: &str, timeout: Duration) -> PingStatus {
+    let url = format!("mysql://{}:{}@{}", user, password, addr);
+    let probe = async {
+        let pool = mysql_async::Pool::new(url.as_str());
+        let mut conn = pool.get_conn().await?;
+        let _: Option<u8> = conn.query_first("SELECT 1").await?;
+        pool.disconnect().await?;
+        Ok::<_, mysql_async::Error>(())
+    };
+
+    match tokio::time::timeout(timeout, probe).await {
+        Ok(Ok(())) => PingStatus::PingOk,
+        Ok(Err(err)) => {
+            warn!(addr, %err, "ping monitor query failed");
+            PingStatus::PingNotOk
+        }
+        Err(_) => {
+            warn!(addr, "ping monitor timed out");
+            PingStatus::PingNotOk
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright 2022 SphereEx Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Periodically reads `@@read_only` off every configured endpoint so
+//! `MonitorReconcile::report` can notice when MHA (or a manual failover)
+//! promoted a former slave to master.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use tracing::warn;
+
+use crate::{
+    config::{Discovery, ReadWriteSplittingDynamic},
+    discovery::monitor_response_ring::{publish_latest, MonitorResponseProducer},
+    readwritesplitting::ReadWriteEndpoint,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeRole {
+    Master,
+    Slave,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReadOnlyMonitorResponse {
+    pub roles: HashMap<String, NodeRole>,
+}
+
+pub struct ReadOnlyMonitor {
+    config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+    rw_endpoint: ReadWriteEndpoint,
+    producer: MonitorResponseProducer<ReadOnlyMonitorResponse>,
+}
+
+impl ReadOnlyMonitor {
+    pub fn new(
+        config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+        rw_endpoint: ReadWriteEndpoint,
+        producer: MonitorResponseProducer<ReadOnlyMonitorResponse>,
+    ) -> Self {
+        ReadOnlyMonitor {
+            config,
+            rw_endpoint,
+            producer,
+        }
+    }
+
+    /// Spawns the monitor loop. Reloads `read_only_period`/`read_only_timeout`
+    /// from the live config every iteration, so a config swap takes effect
+    /// without restarting this task.
+    pub fn spawn(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let mha = match &self.config.load_full().discovery {
+                    Discovery::Mha(mha) => mha.clone(),
+                    Discovery::GroupReplication(_) => {
+                        tokio::time::sleep(Duration::from_millis(1000)).await;
+                        continue;
+                    }
+                };
+
+                let timeout = Duration::from_millis(mha.read_only_timeout);
+                let mut roles = HashMap::new();
+                for endpoint in self
+                    .rw_endpoint
+                    .readwrite
+                    .iter()
+                    .chain(self.rw_endpoint.read.iter())
+                {
+                    if let Some(role) =
+                        read_only(&endpoint.addr, &mha.user, &mha.password, timeout).await
+                    {
+                        roles.insert(endpoint.addr.clone(), role);
+                    }
+                }
+
+                publish_latest(&mut self.producer, ReadOnlyMonitorResponse { roles });
+                tokio::time::sleep(Duration::from_millis(mha.read_only_period)).await;
+            }
+        })
+    }
+}
+
+async fn read_only(addr: &str, user: &str, password: &str, timeout: Duration) -> Option<NodeRole> {
+    let url = format!("mysql://{}:{}@{}", user, password, addr);
+    let probe = async {
+        let pool = mysql_async::Pool::new(url.as_str());
+        let mut conn = pool.get_conn().await?;
+        let read_only: Option<u8> = conn.query_first("SELECT @@read_only").await?;
+        pool.disconnect().await?;
+        Ok::<_, mysql_async::Error>(read_only)
+    };
+
+    match tokio::time::timeout(timeout, probe).await {
+        Ok(Ok(Some(0))) => Some(NodeRole::Master),
+        Ok(Ok(Some(_))) => Some(NodeRole::Slave),
+        Ok(Ok(None)) => None,
+        Ok(Err(err)) => {
+            warn!(addr, %err, "read_only monitor query failed");
+            None
+        }
+        Err(_) => {
+            warn!(addr, "read_only monitor timed out");
+            None
+        }
+    }
+}
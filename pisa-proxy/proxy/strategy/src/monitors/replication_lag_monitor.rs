@@ -0,0 +1,124 @@
+// Copyright 2022 SphereEx Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Periodically reads `SHOW SLAVE STATUS` off every read endpoint and
+//! compares `Seconds_Behind_Master` against `max_replication_lag`, so
+//! `MonitorReconcile::report` can drop or re-weight lagging replicas.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use tracing::warn;
+
+use crate::{
+    config::{Discovery, ReadWriteSplittingDynamic},
+    discovery::monitor_response_ring::{publish_latest, MonitorResponseProducer},
+    readwritesplitting::ReadWriteEndpoint,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplicationLagStatus {
+    /// Whether `Seconds_Behind_Master` exceeded `max_replication_lag`.
+    pub is_latency: bool,
+    pub seconds_behind_master: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplicationLagMonitorResponse {
+    pub latency: HashMap<String, ReplicationLagStatus>,
+}
+
+pub struct ReplicationLagMonitor {
+    config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+    rw_endpoint: ReadWriteEndpoint,
+    producer: MonitorResponseProducer<ReplicationLagMonitorResponse>,
+}
+
+impl ReplicationLagMonitor {
+    pub fn new(
+        config: Arc<ArcSwap<ReadWriteSplittingDynamic>>,
+        rw_endpoint: ReadWriteEndpoint,
+        producer: MonitorResponseProducer<ReplicationLagMonitorResponse>,
+    ) -> Self {
+        ReplicationLagMonitor {
+            config,
+            rw_endpoint,
+            producer,
+        }
+    }
+
+    /// Spawns the monitor loop. Reloads `replication_lag_period`/
+    /// `replication_lag_timeout`/`max_replication_lag` from the live config
+    /// every iteration, so a config swap takes effect without restarting
+    /// this task.
+    pub fn spawn(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let mha = match &self.config.load_full().discovery {
+                    Discovery::Mha(mha) => mha.clone(),
+                    Discovery::GroupReplication(_) => {
+                        tokio::time::sleep(Duration::from_millis(1000)).await;
+                        continue;
+                    }
+                };
+
+                let timeout = Duration::from_millis(mha.replication_lag_timeout);
+                let mut latency = HashMap::new();
+                for endpoint in &self.rw_endpoint.read {
+                    if let Some(seconds_behind_master) =
+                        slave_status(&endpoint.addr, &mha.user, &mha.password, timeout).await
+                    {
+                        latency.insert(
+                            endpoint.addr.clone(),
+                            ReplicationLagStatus {
+                                is_latency: seconds_behind_master > mha.max_replication_lag / 1000,
+                                seconds_behind_master: Some(seconds_behind_master),
+                            },
+                        );
+                    }
+                }
+
+                publish_latest(
+                    &mut self.producer,
+                    ReplicationLagMonitorResponse { latency },
+                );
+                tokio::time::sleep(Duration::from_millis(mha.replication_lag_period)).await;
+            }
+        })
+    }
+}
+
+async fn slave_status(addr: &str, user: &str, password: &str, timeout: Duration) -> Option<u64> {
+    let url = format!("mysql://{}:{}@{}", user, password, addr);
+    let probe = async {
+        let pool = mysql_async::Pool::new(url.as_str());
+        let mut conn = pool.get_conn().await?;
+        let row: Option<mysql_async::Row> = conn.query_first("SHOW SLAVE STATUS").await?;
+        pool.disconnect().await?;
+        Ok::<_, mysql_async::Error>(row)
+    };
+
+    match tokio::time::timeout(timeout, probe).await {
+        Ok(Ok(Some(row))) => row.get("Seconds_Behind_Master"),
+        Ok(Ok(None)) => None,
+        Ok(Err(err)) => {
+            warn!(addr, %err, "replication_lag monitor query failed");
+            None
+        }
+        Err(_) => {
+            warn!(addr, "replication_lag monitor timed out");
+            None
+        }
+    }
+}
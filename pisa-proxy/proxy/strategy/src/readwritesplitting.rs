@@ -0,0 +1,160 @@
+// Copyright 2022 SphereEx Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The read-write endpoint pool that [`crate::discovery::monitor_reconcile::MonitorReconcile`]
+//! rebuilds on every topology change, plus the bootstrap glue that spawns
+//! the monitor tasks feeding it.
+
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+
+use arc_swap::ArcSwap;
+
+use crate::{
+    config::ReadWriteSplittingDynamic,
+    discovery::{
+        audit::AuditRing, monitor_reconcile::MonitorReconcile,
+        monitor_response_ring::monitor_response_ring,
+    },
+    monitors::{
+        connect_monitor::ConnectMonitor, group_replication_monitor::GroupReplicationMonitor,
+        ping_monitor::PingMonitor, read_only_monitor::ReadOnlyMonitor,
+        replication_lag_monitor::ReplicationLagMonitor,
+    },
+};
+
+/// A single backend MySQL instance known to a `ReadWriteEndpoint`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Endpoint {
+    pub addr: String,
+    /// Routing weight among same-role endpoints. Ramped up/down by
+    /// `MonitorReconcile::report` based on replication lag; `0` means
+    /// "not yet scored", and is treated the same as the lowest weight.
+    pub weight: u64,
+}
+
+/// The current readwrite/read split, as last reconciled from the
+/// configured monitors.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ReadWriteEndpoint {
+    pub readwrite: Vec<Endpoint>,
+    pub read: Vec<Endpoint>,
+}
+
+impl ReadWriteEndpoint {
+    /// Picks which read replica a query should be routed to, weighted by
+    /// `Endpoint::weight` via `selector`. The caller owns `selector` across
+    /// calls so its smoothing state persists between queries.
+    pub fn pick_read<'a>(&'a self, selector: &WeightedReadSelector) -> Option<&'a Endpoint> {
+        selector.select(&self.read)
+    }
+}
+
+/// Picks among same-role endpoints in proportion to `Endpoint::weight`,
+/// using smooth weighted round-robin (the same algorithm nginx's
+/// `weight=` load-balancing directive uses).
+///
+/// The `loadbalance::balance::AlgorithmName` that `RegexRule`/`GenericRule`
+/// carry selects how a *query* is routed to the readwrite-vs-read split;
+/// it's a static, config-time choice and that crate isn't part of this
+/// checkout. Which *replica* serves a read is a runtime signal computed
+/// here from monitor feedback, so it's balanced independently, downstream
+/// of that choice.
+#[derive(Debug, Default)]
+pub struct WeightedReadSelector {
+    current_weights: Mutex<HashMap<String, i64>>,
+}
+
+impl WeightedReadSelector {
+    pub fn new() -> Self {
+        WeightedReadSelector {
+            current_weights: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the next endpoint to route a read to, or `None` if `read` is
+    /// empty. Endpoints with `weight == 0` are treated as weight `1` so a
+    /// freshly-seen node isn't starved before its first lag measurement.
+    pub fn select<'a>(&self, read: &'a [Endpoint]) -> Option<&'a Endpoint> {
+        if read.is_empty() {
+            return None;
+        }
+
+        let mut current = self.current_weights.lock().unwrap();
+        current.retain(|addr, _| read.iter().any(|e| &e.addr == addr));
+
+        let total: i64 = read.iter().map(|e| e.weight.max(1) as i64).sum();
+        let mut best_idx = 0;
+        let mut best_weight = i64::MIN;
+        for (i, endpoint) in read.iter().enumerate() {
+            let current_weight = current.entry(endpoint.addr.clone()).or_insert(0);
+            *current_weight += endpoint.weight.max(1) as i64;
+            if *current_weight > best_weight {
+                best_weight = *current_weight;
+                best_idx = i;
+            }
+        }
+
+        if let Some(current_weight) = current.get_mut(&read[best_idx].addr) {
+            *current_weight -= total;
+        }
+        Some(&read[best_idx])
+    }
+}
+
+/// Builds the monitor response rings, spawns every monitor task against a
+/// shared config handle, and starts the reconcile loop.
+///
+/// Every monitor is constructed with the same `Arc<ArcSwap<_>>` handed out
+/// by `reconcile.config_handle()`, so pushing a new `ReadWriteSplittingDynamic`
+/// via `MonitorReconcile::swap_config` takes effect in already-running
+/// monitors on their next tick, not just in the reconcile loop.
+///
+/// `rw_endpoint` is the statically-configured starting pool; the returned
+/// receiver yields a fresh `ReadWriteEndpoint` each time a monitor observes
+/// a topology change. `audit_ring` can be queried to answer "why did the
+/// master change at time T?".
+pub fn spawn_read_write_splitting(
+    config: ReadWriteSplittingDynamic,
+    rw_endpoint: ReadWriteEndpoint,
+    monitor_interval: u64,
+) -> (crossbeam_channel::Receiver<ReadWriteEndpoint>, AuditRing) {
+    let (mut reconcile, audit_ring) = MonitorReconcile::new(config, rw_endpoint.clone());
+    let config_handle: Arc<ArcSwap<ReadWriteSplittingDynamic>> = reconcile.config_handle();
+
+    let (producers, consumers) = monitor_response_ring();
+
+    ConnectMonitor::new(
+        config_handle.clone(),
+        rw_endpoint.clone(),
+        producers.connect,
+    )
+    .spawn();
+    PingMonitor::new(config_handle.clone(), rw_endpoint.clone(), producers.ping).spawn();
+    ReadOnlyMonitor::new(
+        config_handle.clone(),
+        rw_endpoint.clone(),
+        producers.read_only,
+    )
+    .spawn();
+    ReplicationLagMonitor::new(
+        config_handle.clone(),
+        rw_endpoint.clone(),
+        producers.replication_lag,
+    )
+    .spawn();
+    GroupReplicationMonitor::new(config_handle, rw_endpoint, producers.group_replication).spawn();
+
+    let rx = reconcile.start_monitor_reconcile(monitor_interval, consumers);
+    (rx, audit_ring)
+}